@@ -1,69 +1,364 @@
+use std::borrow::Cow;
+
 use nom::{
     branch::alt,
     bytes::complete::tag as complete_tag,
     bytes::streaming::{tag, take_till},
     character::streaming::{space0, space1},
     combinator::{map, not, peek},
+    error::{ContextError, ErrorKind, ParseError},
     sequence::tuple,
     IResult,
 };
 
+/// The specific way a header failed to parse
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderErrorKind {
+    /// No `:` was found separating a name from its value
+    MissingColon,
+    /// The header name was preceded by whitespace
+    SpaceBeforeColon,
+    /// A folded continuation line could not be parsed
+    MalformedFold,
+    /// The line was not terminated by a recognized EOL sequence
+    BadEol,
+    /// `HeaderConfig::allow_empty_name` is `false` and the name was empty
+    EmptyNameRejected,
+    /// `HeaderConfig::require_space_after_colon` is `true` and no space
+    /// followed the colon
+    MissingSpaceAfterColon,
+    /// `HeaderConfig::allow_bare_lf` is `false` and the value was terminated
+    /// by a bare `\r`, a bare `\n`, or the deformed `\n\r\r\n` sequence
+    BareLfRejected,
+    /// `HeaderConfig::allow_obs_fold` is `false` and the value used obs-fold
+    ObsFoldRejected,
+    /// A single header exceeded `HeaderConfig::max_header_len`
+    HeaderTooLong,
+    /// The header block exceeded `HeaderConfig::max_headers`
+    TooManyHeaders,
+    /// The header block exceeded `HeaderConfig::max_block_len`
+    BlockTooLong,
+    /// Any other nom-internal failure, tagged with its `ErrorKind`
+    Nom(ErrorKind),
+}
+
+/// Error produced while parsing a header block, carrying the offending
+/// header's name and index (when known) and the byte offset within the
+/// original input where parsing stalled.
 #[derive(Debug, PartialEq)]
-pub struct Name {
-    pub name: Vec<u8>,
+pub struct HeaderError<'a> {
+    pub kind: HeaderErrorKind,
+    /// The unparsed tail of the original input at the point of failure
+    pub remaining: &'a [u8],
+    /// The name of the header being parsed when the error occurred, if known
+    pub name: Option<&'a [u8]>,
+    /// The index (0-based) of the header being parsed, set by `headers()`
+    pub index: Option<usize>,
+}
+
+impl<'a> HeaderError<'a> {
+    /// The byte offset of the failure within `original`, which must be the
+    /// same buffer (or a prefix of it) that this error's `remaining` slice
+    /// was sliced from.
+    pub fn offset(&self, original: &[u8]) -> usize {
+        original.len() - self.remaining.len()
+    }
+}
+
+impl<'a> ParseError<&'a [u8]> for HeaderError<'a> {
+    fn from_error_kind(input: &'a [u8], kind: ErrorKind) -> Self {
+        HeaderError {
+            kind: HeaderErrorKind::Nom(kind),
+            remaining: input,
+            name: None,
+            index: None,
+        }
+    }
+
+    fn append(_input: &'a [u8], _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+impl<'a> ContextError<&'a [u8]> for HeaderError<'a> {}
+
+/// Replace the `kind` of an error, leaving an `Incomplete` untouched
+fn with_kind<'a>(e: nom::Err<HeaderError<'a>>, kind: HeaderErrorKind) -> nom::Err<HeaderError<'a>> {
+    map_header_err(e, |err| HeaderError { kind, ..err })
+}
+
+/// Attach the name of the header being parsed to an error, leaving an
+/// `Incomplete` untouched
+fn with_name<'a>(e: nom::Err<HeaderError<'a>>, name: &'a [u8]) -> nom::Err<HeaderError<'a>> {
+    map_header_err(e, |err| HeaderError {
+        name: Some(name),
+        ..err
+    })
+}
+
+/// Attach the index of the header being parsed to an error, leaving an
+/// `Incomplete` untouched
+fn with_index(e: nom::Err<HeaderError<'_>>, index: usize) -> nom::Err<HeaderError<'_>> {
+    map_header_err(e, |err| HeaderError {
+        index: Some(index),
+        ..err
+    })
+}
+
+fn map_header_err<'a>(
+    e: nom::Err<HeaderError<'a>>,
+    f: impl FnOnce(HeaderError<'a>) -> HeaderError<'a>,
+) -> nom::Err<HeaderError<'a>> {
+    match e {
+        nom::Err::Error(err) => nom::Err::Error(f(err)),
+        nom::Err::Failure(err) => nom::Err::Failure(f(err)),
+        incomplete @ nom::Err::Incomplete(_) => incomplete,
+    }
+}
+
+/// Name was empty (eg. `: value`)
+pub const NAME_EMPTY: u8 = 0b0000_0001;
+/// Name started with a whitespace-like control byte that `not(space1)` does
+/// not explicitly guard against (only plain space/tab are rejected outright)
+pub const NAME_LEADING_WHITESPACE: u8 = 0b0000_0010;
+/// Name contains bytes outside the RFC 7230 `token` character set
+pub const NAME_INVALID_CHARS: u8 = 0b0000_0100;
+
+/// Value used obs-fold (a line break followed by whitespace)
+pub const VALUE_FOLDED: u8 = 0b0000_0001;
+/// A line of the value was terminated by a bare `\n` instead of `\r\n`
+pub const VALUE_LF_ONLY: u8 = 0b0000_0010;
+/// A line of the value was terminated by a bare `\r` instead of `\r\n`
+pub const VALUE_CR_ONLY: u8 = 0b0000_0100;
+/// A line of the value was terminated by the deformed `\n\r\r\n` sequence
+pub const VALUE_DEFORMED_EOL: u8 = 0b0000_1000;
+/// Value contains NUL or another control byte (other than HTAB)
+pub const VALUE_CONTROL_CHARS: u8 = 0b0001_0000;
+
+/// Limits and leniency switches for parsing a header block.
+///
+/// The streaming combinators in this module have no notion of "too much
+/// data" on their own, so a caller feeding them an attacker-controlled
+/// stream needs this to bound worst-case memory and header count.
+/// The length caps are checked only against headers that have been parsed
+/// in full; an unfinished header at the end of the buffer is not measured,
+/// whatever its length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderConfig {
+    /// Maximum number of headers allowed in one block
+    pub max_headers: usize,
+    /// Maximum length, in bytes, of a single header as it appears in the
+    /// input (name, separator, value, fold bytes and line terminators
+    /// together)
+    pub max_header_len: usize,
+    /// Maximum total length, in bytes, of the whole header block
+    pub max_block_len: usize,
+    /// Accept a bare `\r`, a bare `\n`, or the deformed `\n\r\r\n` sequence as
+    /// a line terminator instead of requiring `\r\n`
+    pub allow_bare_lf: bool,
+    /// Accept obs-fold (RFC 7230 section 3.2.4) continuation lines
+    pub allow_obs_fold: bool,
+    /// Accept a header with an empty name (eg. `: value`)
+    pub allow_empty_name: bool,
+    /// Require at least one space between the colon and the value
+    pub require_space_after_colon: bool,
+}
+
+impl HeaderConfig {
+    /// Accepts everything this parser is capable of parsing, with no limits.
+    /// This is the configuration `headers()` uses.
+    pub fn lenient() -> Self {
+        HeaderConfig {
+            max_headers: usize::MAX,
+            max_header_len: usize::MAX,
+            max_block_len: usize::MAX,
+            allow_bare_lf: true,
+            allow_obs_fold: true,
+            allow_empty_name: true,
+            require_space_after_colon: false,
+        }
+    }
+
+    /// Turns off every leniency switch (bare/deformed line endings, obs-fold,
+    /// empty names, no space after the colon) and caps header count/length
+    /// to reasonable bounds for attacker-controlled input. Names with
+    /// non-token bytes and values with control bytes are still accepted;
+    /// they are only reported through the `flags` fields.
+    pub fn strict() -> Self {
+        HeaderConfig {
+            max_headers: 100,
+            max_header_len: 8 * 1024,
+            max_block_len: 64 * 1024,
+            allow_bare_lf: false,
+            allow_obs_fold: false,
+            allow_empty_name: false,
+            require_space_after_colon: true,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Name<'a> {
+    pub name: &'a [u8],
     pub flags: u8,
 }
 
 #[derive(Debug, PartialEq)]
-pub struct Value {
-    pub value: Vec<u8>,
+pub struct Value<'a> {
+    pub value: Cow<'a, [u8]>,
     pub flags: u8,
 }
 
 #[derive(Debug, PartialEq)]
-pub struct Header {
-    pub name: Name,
-    pub value: Value,
+pub struct Header<'a> {
+    pub name: Name<'a>,
+    pub value: Value<'a>,
+}
+
+/// Owned copy of a `Name`, valid independently of the input buffer.
+#[derive(Debug, PartialEq, Clone)]
+pub struct OwnedName {
+    pub name: Vec<u8>,
+    pub flags: u8,
+}
+
+/// Owned copy of a `Value`, valid independently of the input buffer.
+#[derive(Debug, PartialEq, Clone)]
+pub struct OwnedValue {
+    pub value: Vec<u8>,
+    pub flags: u8,
+}
+
+/// Owned copy of a `Header`, valid independently of the input buffer.
+#[derive(Debug, PartialEq, Clone)]
+pub struct OwnedHeader {
+    pub name: OwnedName,
+    pub value: OwnedValue,
+}
+
+impl<'a> Name<'a> {
+    /// Copy this name's bytes so the result no longer borrows from the input.
+    pub fn to_owned(&self) -> OwnedName {
+        OwnedName {
+            name: self.name.to_vec(),
+            flags: self.flags,
+        }
+    }
+}
+
+impl<'a> Value<'a> {
+    /// Copy this value's bytes so the result no longer borrows from the input.
+    pub fn to_owned(&self) -> OwnedValue {
+        OwnedValue {
+            value: self.value.to_vec(),
+            flags: self.flags,
+        }
+    }
+}
+
+impl<'a> Header<'a> {
+    /// Copy this header's bytes so the result no longer borrows from the input.
+    pub fn to_owned(&self) -> OwnedHeader {
+        OwnedHeader {
+            name: self.name.to_owned(),
+            value: self.value.to_owned(),
+        }
+    }
+}
+
+/// Test if the byte is a valid RFC 7230 `tchar` (token character)
+fn is_token_byte(c: u8) -> bool {
+    c.is_ascii_alphanumeric()
+        || matches!(
+            c,
+            b'!' | b'#'
+                | b'$'
+                | b'%'
+                | b'&'
+                | b'\''
+                | b'*'
+                | b'+'
+                | b'-'
+                | b'.'
+                | b'^'
+                | b'_'
+                | b'`'
+                | b'|'
+                | b'~'
+        )
+}
+
+/// Test if the byte is a whitespace-like control byte that `name()`'s
+/// `not(space1)` check does not already guard against (that check only
+/// rejects a literal leading space or tab)
+fn is_leading_whitespace_control(c: u8) -> bool {
+    matches!(c, 0x0b | 0x0c)
+}
+
+/// Compute anomaly flags for a parsed name
+fn name_flags(name: &[u8]) -> u8 {
+    let mut flags = 0;
+    if name.is_empty() {
+        flags |= NAME_EMPTY;
+    }
+    if name
+        .first()
+        .is_some_and(|&c| is_leading_whitespace_control(c))
+    {
+        flags |= NAME_LEADING_WHITESPACE;
+    }
+    if name.iter().any(|&c| !is_token_byte(c)) {
+        flags |= NAME_INVALID_CHARS;
+    }
+    flags
 }
 
 /// Parse one header name up to the :
-fn name(input: &[u8]) -> IResult<&[u8], Name> {
-    map(
-        tuple((not(space1), take_till(|c| c == b':'))),
-        |(_, n): (_, &[u8])| Name {
-            name: n.into(),
-            flags: 0,
+fn name(input: &[u8]) -> IResult<&[u8], Name<'_>, HeaderError<'_>> {
+    let (rest, _) =
+        not(space1)(input).map_err(|e| with_kind(e, HeaderErrorKind::SpaceBeforeColon))?;
+    let (rest, n) = take_till(|c| c == b':')(rest)?;
+    Ok((
+        rest,
+        Name {
+            name: n,
+            flags: name_flags(n),
         },
-    )(input)
+    ))
 }
 
 /// Parse one complete end of line character or character set
-fn complete_eol(input: &[u8]) -> IResult<&[u8], &[u8]> {
+pub(crate) fn complete_eol(input: &[u8]) -> IResult<&[u8], &[u8], HeaderError<'_>> {
     alt((
         complete_tag(b"\n\r\r\n"),
         complete_tag(b"\r\n"),
         complete_tag(b"\n"),
         complete_tag(b"\r"),
     ))(input)
+    .map_err(|e| with_kind(e, HeaderErrorKind::BadEol))
 }
 
-/// Parse one header end of line, and guarantee that it is not folding
-fn eol(input: &[u8]) -> IResult<&[u8], &[u8]> {
+/// Parse one complete end of line that is guaranteed not to be folding (ie.
+/// not followed by whitespace). This also forces `Incomplete` when the
+/// buffer ends right after the eol, since `complete_eol`'s bare `\r`/`\n`
+/// alternatives would otherwise declare a lone `\r` complete even though a
+/// `\n` completing it into `\r\n` might be about to arrive.
+pub(crate) fn eol(input: &[u8]) -> IResult<&[u8], &[u8], HeaderError<'_>> {
     map(tuple((complete_eol, peek(not(space1)))), |(end, _)| end)(input)
 }
 
 /// Test if the byte is CR or LF
-fn is_eol(c: u8) -> bool {
+pub(crate) fn is_eol(c: u8) -> bool {
     c == b'\r' || c == b'\n'
 }
 
 /// Parse header folding bytes (eol + whitespace)
-fn folding(input: &[u8]) -> IResult<&[u8], (&[u8], &[u8])> {
+fn folding(input: &[u8]) -> IResult<&[u8], (&[u8], &[u8]), HeaderError<'_>> {
     tuple((complete_eol, space1))(input)
 }
 
 /// Parse folding bytes or an eol
-fn folding_or_eol(input: &[u8]) -> IResult<&[u8], (&[u8], Option<&[u8]>)> {
+fn folding_or_eol(input: &[u8]) -> IResult<&[u8], (&[u8], Option<&[u8]>), HeaderError<'_>> {
     if let Ok((rest, (end, fold))) = folding(input) {
         Ok((rest, (end, Some(fold))))
     } else {
@@ -74,72 +369,212 @@ fn folding_or_eol(input: &[u8]) -> IResult<&[u8], (&[u8], Option<&[u8]>)> {
 /// Parse a header value.
 /// Returns the bytes and the value terminator, either eol or folding
 /// eg. (bytes, (eol_bytes, Option<fold_bytes>))
-fn value_bytes(input: &[u8]) -> IResult<&[u8], (&[u8], (&[u8], Option<&[u8]>))> {
+fn value_bytes(input: &[u8]) -> IResult<&[u8], (&[u8], (&[u8], Option<&[u8]>)), HeaderError<'_>> {
     tuple((take_till(is_eol), folding_or_eol))(input)
 }
 
-/// Parse a complete header value, including any folded headers
-fn value(input: &[u8]) -> IResult<&[u8], Value> {
-    let (rest, (val_bytes, (_eol, fold))) = value_bytes(input)?;
+/// Map a matched `complete_eol` terminator to the anomaly flag it represents,
+/// if any (a plain `\r\n` is not an anomaly and maps to 0)
+fn eol_flags(terminator: &[u8]) -> u8 {
+    match terminator {
+        b"\n" => VALUE_LF_ONLY,
+        b"\r" => VALUE_CR_ONLY,
+        b"\n\r\r\n" => VALUE_DEFORMED_EOL,
+        _ => 0,
+    }
+}
 
-    let mut value = val_bytes.to_vec();
-    if fold.is_none() {
-        Ok((rest, Value { value, flags: 0 }))
+/// Test if the byte is a control byte that should not appear in a value
+/// (HTAB is allowed inside values, so it is exempted)
+fn is_value_control_byte(c: u8) -> bool {
+    c != b'\t' && (c.is_ascii_control() || c == 0x7f)
+}
+
+/// Compute the content-related anomaly flags for one value fragment
+fn value_content_flags(bytes: &[u8]) -> u8 {
+    if bytes.iter().any(|&c| is_value_control_byte(c)) {
+        VALUE_CONTROL_CHARS
     } else {
-        let mut i = rest;
-        loop {
-            match value_bytes(i) {
-                Ok((rest, (val_bytes, (_eol, fold)))) => {
-                    i = rest;
-                    value.push(b' ');
-                    value.extend(val_bytes);
-                    if fold.is_none() {
-                        return Ok((rest, Value { value, flags: 0 }));
-                    }
+        0
+    }
+}
+
+/// Parse a complete header value, including any folded headers.
+/// The common unfolded case borrows straight from `input`; folded values are
+/// joined with a space into an owned buffer since they span disjoint slices.
+fn value(input: &[u8]) -> IResult<&[u8], Value<'_>, HeaderError<'_>> {
+    let (rest, (val_bytes, (eol, fold))) = value_bytes(input)?;
+    let mut flags = eol_flags(eol) | value_content_flags(val_bytes);
+
+    if fold.is_none() {
+        return Ok((
+            rest,
+            Value {
+                value: Cow::Borrowed(val_bytes),
+                flags,
+            },
+        ));
+    }
+
+    flags |= VALUE_FOLDED;
+    let mut value = val_bytes.to_vec();
+    let mut i = rest;
+    loop {
+        match value_bytes(i) {
+            Ok((rest, (val_bytes, (eol, fold)))) => {
+                i = rest;
+                flags |= eol_flags(eol) | value_content_flags(val_bytes);
+                value.push(b' ');
+                value.extend(val_bytes);
+                if fold.is_none() {
+                    return Ok((
+                        rest,
+                        Value {
+                            value: Cow::Owned(value),
+                            flags,
+                        },
+                    ));
                 }
-                Err(e) => return Err(e),
             }
+            Err(e) => return Err(with_kind(e, HeaderErrorKind::MalformedFold)),
         }
     }
 }
 
 /// Parse a separator (colon + space) between header name and value
-fn separator(input: &[u8]) -> IResult<&[u8], (&[u8], &[u8])> {
-    tuple((tag(b":"), space0))(input)
+fn separator(input: &[u8]) -> IResult<&[u8], (&[u8], &[u8]), HeaderError<'_>> {
+    let (rest, colon) =
+        tag(b":")(input).map_err(|e| with_kind(e, HeaderErrorKind::MissingColon))?;
+    let (rest, sp) = space0(rest)?;
+    Ok((rest, (colon, sp)))
 }
 
-/// Parse a header name: value
-fn header(input: &[u8]) -> IResult<&[u8], Header> {
-    map(tuple((name, separator, value)), |(name, _, value)| Header {
+/// Build a `HeaderError` of the given `kind`, pointing at `remaining` and
+/// (optionally) the header name being parsed
+fn config_err<'a>(
+    kind: HeaderErrorKind,
+    remaining: &'a [u8],
+    name: Option<&'a [u8]>,
+) -> nom::Err<HeaderError<'a>> {
+    nom::Err::Error(HeaderError {
+        kind,
+        remaining,
         name,
-        value,
-    })(input)
+        index: None,
+    })
 }
 
-/// Parse multiple headers and indicate if end of headers was found
-pub fn headers(input: &[u8]) -> IResult<&[u8], (Vec<Header>, bool)> {
-    let (rest, head) = header(input)?;
+/// Parse one `name: value` header and enforce `config`'s leniency switches
+/// and the single-header length cap
+fn header_with<'a>(
+    input: &'a [u8],
+    config: &HeaderConfig,
+) -> IResult<&'a [u8], Header<'a>, HeaderError<'a>> {
+    let (rest, name) = name(input)?;
+    if !config.allow_empty_name && name.flags & NAME_EMPTY != 0 {
+        return Err(config_err(
+            HeaderErrorKind::EmptyNameRejected,
+            rest,
+            Some(name.name),
+        ));
+    }
+
+    let (rest, (_colon, sp)) = separator(rest).map_err(|e| with_name(e, name.name))?;
+    if config.require_space_after_colon && sp.is_empty() {
+        return Err(config_err(
+            HeaderErrorKind::MissingSpaceAfterColon,
+            rest,
+            Some(name.name),
+        ));
+    }
+
+    let (rest, value) = value(rest).map_err(|e| with_name(e, name.name))?;
+    if !config.allow_bare_lf
+        && value.flags & (VALUE_LF_ONLY | VALUE_CR_ONLY | VALUE_DEFORMED_EOL) != 0
+    {
+        return Err(config_err(
+            HeaderErrorKind::BareLfRejected,
+            rest,
+            Some(name.name),
+        ));
+    }
+    if !config.allow_obs_fold && value.flags & VALUE_FOLDED != 0 {
+        return Err(config_err(
+            HeaderErrorKind::ObsFoldRejected,
+            rest,
+            Some(name.name),
+        ));
+    }
+    if input.len() - rest.len() > config.max_header_len {
+        return Err(config_err(
+            HeaderErrorKind::HeaderTooLong,
+            rest,
+            Some(name.name),
+        ));
+    }
+
+    Ok((rest, Header { name, value }))
+}
+
+/// Parse multiple headers, enforcing `config`'s limits and leniency
+/// switches, and indicate if end of headers was found
+pub fn headers_with<'a>(
+    input: &'a [u8],
+    config: &HeaderConfig,
+) -> IResult<&'a [u8], (Vec<Header<'a>>, bool), HeaderError<'a>> {
+    let (rest, head) = header_with(input, config).map_err(|e| with_index(e, 0))?;
     let mut out = Vec::with_capacity(16);
     out.push(head);
+    if out.len() > config.max_headers {
+        return Err(with_index(
+            config_err(HeaderErrorKind::TooManyHeaders, rest, None),
+            out.len() - 1,
+        ));
+    }
+    if input.len() - rest.len() > config.max_block_len {
+        return Err(with_index(
+            config_err(HeaderErrorKind::BlockTooLong, rest, None),
+            out.len() - 1,
+        ));
+    }
     if let Ok((rest, _eoh)) = complete_eol(rest) {
         return Ok((rest, (out, true)));
     }
     let mut i = rest;
+    let mut index = 1;
     loop {
-        match header(i) {
+        match header_with(i, config) {
             Ok((rest, head)) => {
                 i = rest;
                 out.push(head);
+                index += 1;
+                if out.len() > config.max_headers {
+                    return Err(with_index(
+                        config_err(HeaderErrorKind::TooManyHeaders, rest, None),
+                        out.len() - 1,
+                    ));
+                }
+                if input.len() - rest.len() > config.max_block_len {
+                    return Err(with_index(
+                        config_err(HeaderErrorKind::BlockTooLong, rest, None),
+                        out.len() - 1,
+                    ));
+                }
                 if let Ok((rest, _eoh)) = complete_eol(rest) {
                     return Ok((rest, (out, true)));
                 }
             }
             Err(nom::Err::Incomplete(_)) => return Ok((rest, (out, false))),
-            Err(e) => return Err(e),
+            Err(e) => return Err(with_index(e, index)),
         }
     }
 }
 
+/// Parse multiple headers and indicate if end of headers was found
+pub fn headers(input: &[u8]) -> IResult<&[u8], (Vec<Header<'_>>, bool), HeaderError<'_>> {
+    headers_with(input, &HeaderConfig::lenient())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -160,31 +595,31 @@ mod test {
                     vec![
                         Header {
                             name: Name {
-                                name: b"k1".to_vec(),
+                                name: b"k1",
                                 flags: 0
                             },
                             value: Value {
-                                value: b"v1".to_vec(),
+                                value: Cow::Borrowed(b"v1"),
                                 flags: 0
                             },
                         },
                         Header {
                             name: Name {
-                                name: b"".to_vec(),
-                                flags: 0
+                                name: b"",
+                                flags: NAME_EMPTY
                             },
                             value: Value {
-                                value: b"v2 v2+".to_vec(),
-                                flags: 0
+                                value: Cow::Owned(b"v2 v2+".to_vec()),
+                                flags: VALUE_FOLDED
                             },
                         },
                         Header {
                             name: Name {
-                                name: b"k3".to_vec(),
+                                name: b"k3",
                                 flags: 0
                             },
                             value: Value {
-                                value: b"v3".to_vec(),
+                                value: Cow::Borrowed(b"v3"),
                                 flags: 0
                             },
                         }
@@ -200,11 +635,11 @@ mod test {
                 (
                     vec![Header {
                         name: Name {
-                            name: b"k1".to_vec(),
+                            name: b"k1",
                             flags: 0
                         },
                         value: Value {
-                            value: b"v1".to_vec(),
+                            value: Cow::Borrowed(b"v1"),
                             flags: 0
                         },
                     },],
@@ -216,36 +651,37 @@ mod test {
 
     #[test]
     fn test_header() {
-        assert!(header(b"K: V").is_err());
-        assert!(header(b"K: V\r\n").is_err());
+        let lenient = HeaderConfig::lenient();
+        assert!(header_with(b"K: V", &lenient).is_err());
+        assert!(header_with(b"K: V\r\n", &lenient).is_err());
         assert_eq!(
-            header(b"K: V\r\n\r\n"),
+            header_with(b"K: V\r\n\r\n", &lenient),
             Ok((
                 b!("\r\n"),
                 Header {
                     name: Name {
-                        name: b"K".to_vec(),
+                        name: b"K",
                         flags: 0
                     },
                     value: Value {
-                        value: b"V".to_vec(),
+                        value: Cow::Borrowed(b"V"),
                         flags: 0
                     },
                 }
             ))
         );
         assert_eq!(
-            header(b"K: V\r\n a\r\n l\r\n u\r\n\te\r\n\r\n"),
+            header_with(b"K: V\r\n a\r\n l\r\n u\r\n\te\r\n\r\n", &lenient),
             Ok((
                 b!("\r\n"),
                 Header {
                     name: Name {
-                        name: b"K".to_vec(),
+                        name: b"K",
                         flags: 0
                     },
                     value: Value {
-                        value: b"V a l u e".to_vec(),
-                        flags: 0
+                        value: Cow::Owned(b"V a l u e".to_vec()),
+                        flags: VALUE_FOLDED
                     },
                 }
             ))
@@ -269,12 +705,30 @@ mod test {
 
     #[test]
     fn test_name() {
-        assert_eq!(name(b"Hello: world").unwrap().1.name, b"Hello".to_vec());
-        assert_eq!(name(b": world").unwrap().1.name, b"".to_vec());
+        let (_, parsed) = name(b"Hello: world").unwrap();
+        assert_eq!(parsed.name, b"Hello");
+        assert_eq!(parsed.flags, 0);
+        let (_, parsed) = name(b": world").unwrap();
+        assert_eq!(parsed.name, b"");
+        assert_eq!(parsed.flags, NAME_EMPTY);
         assert!(name(b" Hello: world").is_err());
         assert!(name(b"Hello").is_err());
     }
 
+    #[test]
+    fn test_name_flags() {
+        assert_eq!(name_flags(b"Hello"), 0);
+        assert_eq!(name_flags(b""), NAME_EMPTY);
+        assert_eq!(name_flags(b"He llo"), NAME_INVALID_CHARS);
+        assert_eq!(
+            name_flags(b"\x0bHello"),
+            NAME_LEADING_WHITESPACE | NAME_INVALID_CHARS
+        );
+        // A leading control byte that isn't whitespace-like (eg. NUL) should
+        // still be flagged as an invalid char, but not as leading whitespace.
+        assert_eq!(name_flags(b"\x00Hello"), NAME_INVALID_CHARS);
+    }
+
     #[test]
     fn test_eol() {
         assert!(eol(b"test").is_err());
@@ -410,7 +864,7 @@ mod test {
             Ok((
                 b!("next:"),
                 Value {
-                    value: b"value".to_vec(),
+                    value: Cow::Borrowed(b"value"),
                     flags: 0
                 }
             ))
@@ -420,8 +874,8 @@ mod test {
             Ok((
                 b!("\r\n"),
                 Value {
-                    value: b"value more".to_vec(),
-                    flags: 0
+                    value: Cow::Owned(b"value more".to_vec()),
+                    flags: VALUE_FOLDED
                 }
             ))
         );
@@ -430,8 +884,8 @@ mod test {
             Ok((
                 b!("next:"),
                 Value {
-                    value: b"value more and more".to_vec(),
-                    flags: 0
+                    value: Cow::Owned(b"value more and more".to_vec()),
+                    flags: VALUE_FOLDED
                 }
             ))
         );
@@ -440,8 +894,8 @@ mod test {
             Ok((
                 b!("\r\n"),
                 Value {
-                    value: b"value more and more".to_vec(),
-                    flags: 0
+                    value: Cow::Owned(b"value more and more".to_vec()),
+                    flags: VALUE_FOLDED | VALUE_LF_ONLY | VALUE_DEFORMED_EOL
                 }
             ))
         );
@@ -450,10 +904,182 @@ mod test {
             Ok((
                 b!("next:"),
                 Value {
-                    value: b"value more and more".to_vec(),
-                    flags: 0
+                    value: Cow::Owned(b"value more and more".to_vec()),
+                    flags: VALUE_FOLDED | VALUE_LF_ONLY
                 }
             ))
         );
     }
+
+    #[test]
+    fn test_eol_flags() {
+        assert_eq!(eol_flags(b"\r\n"), 0);
+        assert_eq!(eol_flags(b"\n"), VALUE_LF_ONLY);
+        assert_eq!(eol_flags(b"\r"), VALUE_CR_ONLY);
+        assert_eq!(eol_flags(b"\n\r\r\n"), VALUE_DEFORMED_EOL);
+    }
+
+    #[test]
+    fn test_value_content_flags() {
+        assert_eq!(value_content_flags(b"plain value"), 0);
+        assert_eq!(value_content_flags(b"tab\tallowed"), 0);
+        assert_eq!(value_content_flags(b"nul\0byte"), VALUE_CONTROL_CHARS);
+    }
+
+    #[test]
+    fn test_separator_error_kind() {
+        match separator(b"value") {
+            Err(nom::Err::Error(e)) => assert_eq!(e.kind, HeaderErrorKind::MissingColon),
+            other => panic!("expected a MissingColon error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_name_error_kind() {
+        match name(b" Hello: world") {
+            Err(nom::Err::Error(e)) => assert_eq!(e.kind, HeaderErrorKind::SpaceBeforeColon),
+            other => panic!("expected a SpaceBeforeColon error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_complete_eol_error_kind() {
+        match complete_eol(b"xx") {
+            Err(nom::Err::Error(e)) => assert_eq!(e.kind, HeaderErrorKind::BadEol),
+            other => panic!("expected a BadEol error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_headers_error_index_and_offset() {
+        // A leading space right after an eol is always obs-fold per this
+        // crate's grammar, so a genuinely new second header can only fail
+        // independently of the first when obs-fold isn't what trips it up.
+        let input: &[u8] = b"k1: v1\r\nk2:v2\r\n\r\n";
+        let config = HeaderConfig {
+            require_space_after_colon: true,
+            ..HeaderConfig::lenient()
+        };
+        match headers_with(input, &config) {
+            Err(nom::Err::Error(e)) => {
+                assert_eq!(e.kind, HeaderErrorKind::MissingSpaceAfterColon);
+                assert_eq!(e.name, Some(b"k2".as_slice()));
+                assert_eq!(e.index, Some(1));
+                assert_eq!(e.offset(input), 11);
+            }
+            other => panic!(
+                "expected an indexed MissingSpaceAfterColon error, got {:?}",
+                other
+            ),
+        }
+    }
+
+    #[test]
+    fn test_headers_with_strict_rejects_empty_name() {
+        // The first header needs a space after its colon too, or strict mode
+        // rejects it on that ground before the second header is ever parsed.
+        match headers_with(b"k1: v1\r\n:v2\r\n\r\n", &HeaderConfig::strict()) {
+            Err(nom::Err::Error(e)) => assert_eq!(e.kind, HeaderErrorKind::EmptyNameRejected),
+            other => panic!("expected EmptyNameRejected error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_headers_with_strict_rejects_missing_space() {
+        match headers_with(b"k1:v1\r\n\r\n", &HeaderConfig::strict()) {
+            Err(nom::Err::Error(e)) => {
+                assert_eq!(e.kind, HeaderErrorKind::MissingSpaceAfterColon)
+            }
+            other => panic!("expected MissingSpaceAfterColon error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_headers_with_strict_rejects_bare_lf() {
+        match headers_with(b"k1: v1\nk2: v2\r\n\r\n", &HeaderConfig::strict()) {
+            Err(nom::Err::Error(e)) => assert_eq!(e.kind, HeaderErrorKind::BareLfRejected),
+            other => panic!("expected BareLfRejected error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_headers_with_strict_rejects_deformed_eol() {
+        match headers_with(b"k1: v1\n\r\r\nk2: v2\r\n\r\n", &HeaderConfig::strict()) {
+            Err(nom::Err::Error(e)) => assert_eq!(e.kind, HeaderErrorKind::BareLfRejected),
+            other => panic!("expected BareLfRejected error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_headers_with_strict_rejects_obs_fold() {
+        match headers_with(b"k1: v1\r\n v1+\r\n\r\n", &HeaderConfig::strict()) {
+            Err(nom::Err::Error(e)) => assert_eq!(e.kind, HeaderErrorKind::ObsFoldRejected),
+            other => panic!("expected ObsFoldRejected error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_headers_with_max_headers() {
+        let config = HeaderConfig {
+            max_headers: 1,
+            ..HeaderConfig::lenient()
+        };
+        match headers_with(b"k1: v1\r\nk2: v2\r\n\r\n", &config) {
+            Err(nom::Err::Error(e)) => assert_eq!(e.kind, HeaderErrorKind::TooManyHeaders),
+            other => panic!("expected TooManyHeaders error, got {:?}", other),
+        }
+        assert!(headers_with(b"k1: v1\r\n\r\n", &config).is_ok());
+    }
+
+    #[test]
+    fn test_headers_with_max_header_len() {
+        let config = HeaderConfig {
+            max_header_len: 4,
+            ..HeaderConfig::lenient()
+        };
+        match headers_with(b"k1: v1\r\n\r\n", &config) {
+            Err(nom::Err::Error(e)) => assert_eq!(e.kind, HeaderErrorKind::HeaderTooLong),
+            other => panic!("expected HeaderTooLong error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_headers_with_max_block_len() {
+        let config = HeaderConfig {
+            max_block_len: 4,
+            ..HeaderConfig::lenient()
+        };
+        match headers_with(b"k1: v1\r\nk2: v2\r\n\r\n", &config) {
+            Err(nom::Err::Error(e)) => assert_eq!(e.kind, HeaderErrorKind::BlockTooLong),
+            other => panic!("expected BlockTooLong error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_headers_with_lenient_matches_headers() {
+        let input: &[u8] = b"k1:v1\r\n:v2\r\n v2+\r\nk3: v3\r\n\r\n";
+        assert_eq!(
+            headers_with(input, &HeaderConfig::lenient()),
+            headers(input)
+        );
+    }
+
+    #[test]
+    fn test_to_owned() {
+        let (_, header) = header_with(b"K: V\r\n\r\n", &HeaderConfig::lenient()).unwrap();
+        let owned = header.to_owned();
+        assert_eq!(
+            owned,
+            OwnedHeader {
+                name: OwnedName {
+                    name: b"K".to_vec(),
+                    flags: 0
+                },
+                value: OwnedValue {
+                    value: b"V".to_vec(),
+                    flags: 0
+                },
+            }
+        );
+    }
 }
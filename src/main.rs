@@ -1,4 +1,4 @@
-mod headers;
+use test_headers::headers;
 
 fn main() {
     if let Ok((_rest, (headers, _complete))) = headers::headers(b"Hello: world\r\n\r\n") {
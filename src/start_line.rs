@@ -0,0 +1,282 @@
+use nom::{
+    branch::alt,
+    bytes::streaming::{tag, take_till},
+    character::streaming::digit1,
+    combinator::map,
+    sequence::tuple,
+    IResult,
+};
+
+use crate::headers::{eol, headers, is_eol, Header, HeaderError};
+
+#[derive(Debug, PartialEq)]
+pub struct Version<'a> {
+    pub major: &'a [u8],
+    pub minor: &'a [u8],
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RequestLine<'a> {
+    pub method: &'a [u8],
+    pub uri: &'a [u8],
+    pub version: Version<'a>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct StatusLine<'a> {
+    pub version: Version<'a>,
+    pub code: &'a [u8],
+    pub reason: &'a [u8],
+}
+
+#[derive(Debug, PartialEq)]
+pub enum StartLine<'a> {
+    Request(RequestLine<'a>),
+    Status(StatusLine<'a>),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Message<'a> {
+    pub start: StartLine<'a>,
+    pub headers: Vec<Header<'a>>,
+    pub complete: bool,
+}
+
+/// Test if the byte is a space, the separator between start-line fields
+fn is_space(c: u8) -> bool {
+    c == b' '
+}
+
+/// Parse an `HTTP/major.minor` version token
+fn version(input: &[u8]) -> IResult<&[u8], Version<'_>, HeaderError<'_>> {
+    map(
+        tuple((tag(b"HTTP/"), digit1, tag(b"."), digit1)),
+        |(_, major, _, minor)| Version { major, minor },
+    )(input)
+}
+
+/// Parse a request-line: `METHOD SP request-target SP HTTP-version CRLF`
+pub fn request_line(input: &[u8]) -> IResult<&[u8], RequestLine<'_>, HeaderError<'_>> {
+    map(
+        tuple((
+            take_till(is_space),
+            tag(b" "),
+            take_till(is_space),
+            tag(b" "),
+            version,
+            eol,
+        )),
+        |(method, _, uri, _, version, _eol)| RequestLine {
+            method,
+            uri,
+            version,
+        },
+    )(input)
+}
+
+/// Parse a status-line: `HTTP-version SP status-code SP reason-phrase CRLF`
+pub fn status_line(input: &[u8]) -> IResult<&[u8], StatusLine<'_>, HeaderError<'_>> {
+    map(
+        tuple((
+            version,
+            tag(b" "),
+            digit1,
+            tag(b" "),
+            take_till(is_eol),
+            eol,
+        )),
+        |(version, _, code, _, reason, _eol)| StatusLine {
+            version,
+            code,
+            reason,
+        },
+    )(input)
+}
+
+/// Parse either a request-line or a status-line
+fn start_line(input: &[u8]) -> IResult<&[u8], StartLine<'_>, HeaderError<'_>> {
+    alt((
+        map(request_line, StartLine::Request),
+        map(status_line, StartLine::Status),
+    ))(input)
+}
+
+/// Parse a complete message head: the start-line followed by the headers
+pub fn message(input: &[u8]) -> IResult<&[u8], Message<'_>, HeaderError<'_>> {
+    let (rest, start) = start_line(input)?;
+    let (rest, (headers, complete)) = headers(rest)?;
+    Ok((
+        rest,
+        Message {
+            start,
+            headers,
+            complete,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::headers::{Name, Value};
+    use std::borrow::Cow;
+
+    macro_rules! b {
+        ($b: literal) => {
+            $b.as_bytes()
+        };
+    }
+
+    #[test]
+    fn test_version() {
+        assert!(version(b"HTTP/1").is_err());
+        assert!(version(b"HTTP/1.1").is_err());
+        assert_eq!(
+            version(b"HTTP/1.1 "),
+            Ok((
+                b!(" "),
+                Version {
+                    major: b!("1"),
+                    minor: b!("1")
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_request_line() {
+        assert!(request_line(b"GET / HTTP/1.1").is_err());
+        assert_eq!(
+            request_line(b"GET /index.html HTTP/1.1\r\nHost: example.com\r\n"),
+            Ok((
+                b!("Host: example.com\r\n"),
+                RequestLine {
+                    method: b!("GET"),
+                    uri: b!("/index.html"),
+                    version: Version {
+                        major: b!("1"),
+                        minor: b!("1")
+                    },
+                }
+            ))
+        );
+        assert_eq!(
+            request_line(b"GET / HTTP/1.1\n\r\n"),
+            Ok((
+                b!("\r\n"),
+                RequestLine {
+                    method: b!("GET"),
+                    uri: b!("/"),
+                    version: Version {
+                        major: b!("1"),
+                        minor: b!("1")
+                    },
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_request_line_incomplete_on_ambiguous_bare_cr() {
+        // A buffer cut right before the real `\n` must not be mistaken for a
+        // complete bare-`\r` terminator: more data could still arrive and
+        // turn this into `\r\n`.
+        assert!(matches!(
+            request_line(b"GET / HTTP/1.1\r"),
+            Err(nom::Err::Incomplete(_))
+        ));
+    }
+
+    #[test]
+    fn test_status_line() {
+        assert!(status_line(b"HTTP/1.1 200 OK").is_err());
+        assert_eq!(
+            status_line(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n"),
+            Ok((
+                b!("Content-Length: 0\r\n"),
+                StatusLine {
+                    version: Version {
+                        major: b!("1"),
+                        minor: b!("1")
+                    },
+                    code: b!("200"),
+                    reason: b!("OK"),
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_status_line_incomplete_on_ambiguous_bare_cr() {
+        assert!(matches!(
+            status_line(b"HTTP/1.1 200 OK\r"),
+            Err(nom::Err::Incomplete(_))
+        ));
+    }
+
+    #[test]
+    fn test_message() {
+        assert_eq!(
+            message(b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n"),
+            Ok((
+                b!(""),
+                Message {
+                    start: StartLine::Request(RequestLine {
+                        method: b!("GET"),
+                        uri: b!("/"),
+                        version: Version {
+                            major: b!("1"),
+                            minor: b!("1")
+                        },
+                    }),
+                    headers: vec![Header {
+                        name: Name {
+                            name: b!("Host"),
+                            flags: 0
+                        },
+                        value: Value {
+                            value: Cow::Borrowed(b!("example.com")),
+                            flags: 0
+                        },
+                    }],
+                    complete: true,
+                }
+            ))
+        );
+        assert_eq!(
+            message(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n"),
+            Ok((
+                b!(""),
+                Message {
+                    start: StartLine::Status(StatusLine {
+                        version: Version {
+                            major: b!("1"),
+                            minor: b!("1")
+                        },
+                        code: b!("200"),
+                        reason: b!("OK"),
+                    }),
+                    headers: vec![Header {
+                        name: Name {
+                            name: b!("Content-Length"),
+                            flags: 0
+                        },
+                        value: Value {
+                            value: Cow::Borrowed(b!("0")),
+                            flags: 0
+                        },
+                    }],
+                    complete: true,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_message_incomplete_on_ambiguous_bare_cr() {
+        assert!(matches!(
+            message(b"GET / HTTP/1.1\r"),
+            Err(nom::Err::Incomplete(_))
+        ));
+    }
+}